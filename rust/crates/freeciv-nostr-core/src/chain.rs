@@ -0,0 +1,374 @@
+//! Tamper-evident per-game event chain verification.
+//!
+//! Each non-genesis [`NostrEvent`] produced by [`crate::event::sign_action`] carries an
+//! `["e", "<id>"]` tag pointing at the previous turn's event, much like dereferenceable object ids in
+//! federation protocols. [`ChainVerifier`] ingests a set of events for a single game and
+//! validates the resulting hash-linked chain, or reports exactly which event broke it.
+
+use std::collections::HashMap;
+
+use crate::event::NostrEvent;
+
+/// The ordering and gap report produced by a successful [`ChainVerifier::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainReport {
+    /// Event ids in chain order, genesis first.
+    pub ordered_ids: Vec<String>,
+    /// Turn numbers absent between the genesis and tip turns, for backfill requests.
+    pub missing_turns: Vec<u64>,
+}
+
+/// A defect found while verifying a game's event chain, naming the offending event(s).
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ChainError {
+    /// Every event in the set has an `e` tag, so there is no genesis to start the chain from
+    /// (or the set was empty).
+    #[error("no genesis event found in the given set")]
+    NoGenesis,
+    /// More than one event lacks an `e` tag, so the chain does not have a single root.
+    #[error("multiple genesis events found: {first} and {second}")]
+    MultipleGenesis {
+        /// The first (lexicographically) genesis event id found.
+        first: String,
+        /// The second genesis event id found.
+        second: String,
+    },
+    /// An event's `e` tag points at an id that is not present in the given event set.
+    #[error("event {event_id} references predecessor {predecessor} which is not in the given set")]
+    DanglingPredecessor {
+        /// The event with the unresolvable predecessor.
+        event_id: String,
+        /// The missing predecessor id.
+        predecessor: String,
+    },
+    /// Two events both claim the same predecessor, forking the chain.
+    #[error("events {first} and {second} both claim predecessor {predecessor} (fork)")]
+    Fork {
+        /// The predecessor id claimed by both events.
+        predecessor: String,
+        /// The first (lexicographically) forking event id.
+        first: String,
+        /// The second forking event id.
+        second: String,
+    },
+    /// An event is missing its `["turn", "<n>"]` tag.
+    #[error("event {event_id} is missing a turn tag")]
+    MissingTurnTag {
+        /// The event missing a turn tag.
+        event_id: String,
+    },
+    /// An event's `turn` tag value could not be parsed as a `u64`.
+    #[error("event {event_id} has a malformed turn tag")]
+    MalformedTurnTag {
+        /// The event with the malformed turn tag.
+        event_id: String,
+    },
+    /// A turn number did not strictly increase along the chain.
+    #[error("event {event_id} turn does not strictly increase past predecessor turn {previous_turn}")]
+    NonIncreasingTurn {
+        /// The event whose turn regressed or repeated.
+        event_id: String,
+        /// The predecessor's turn number.
+        previous_turn: u64,
+    },
+    /// One or more events were never reached while walking forward from the genesis, e.g. a
+    /// cycle of events that only reference each other, disconnected from the real chain.
+    #[error("event(s) {event_ids:?} are disconnected from the genesis event")]
+    Disconnected {
+        /// Ids of the events that were never linked into the ordered chain.
+        event_ids: Vec<String>,
+    },
+}
+
+/// Validates hash-linked chains of [`NostrEvent`]s for a single game.
+pub struct ChainVerifier;
+
+impl ChainVerifier {
+    /// Order `events` by their `e`-tag links and `turn` tags, validating that the chain has
+    /// no forks, no dangling predecessors, and strictly increasing turns.
+    ///
+    /// Returns the genesis-first ordering and any turn numbers missing along the way, or the
+    /// specific [`ChainError`] describing what broke the chain.
+    pub fn verify(events: &[NostrEvent]) -> Result<ChainReport, ChainError> {
+        let by_id: HashMap<&str, &NostrEvent> =
+            events.iter().map(|event| (event.id.as_str(), event)).collect();
+
+        let mut children_of: HashMap<&str, Vec<&NostrEvent>> = HashMap::new();
+        let mut genesis_candidates: Vec<&NostrEvent> = Vec::new();
+        for event in events {
+            match predecessor_of(event) {
+                Some(predecessor) => {
+                    if !by_id.contains_key(predecessor.as_str()) {
+                        return Err(ChainError::DanglingPredecessor {
+                            event_id: event.id.clone(),
+                            predecessor,
+                        });
+                    }
+                    children_of.entry(by_id[predecessor.as_str()].id.as_str()).or_default().push(event);
+                }
+                None => genesis_candidates.push(event),
+            }
+        }
+
+        for children in children_of.values() {
+            if children.len() > 1 {
+                let mut ids: Vec<&str> = children.iter().map(|e| e.id.as_str()).collect();
+                ids.sort_unstable();
+                return Err(ChainError::Fork {
+                    predecessor: predecessor_of(children[0]).expect("child has a predecessor"),
+                    first: ids[0].to_string(),
+                    second: ids[1].to_string(),
+                });
+            }
+        }
+
+        let genesis = match genesis_candidates.as_slice() {
+            [] => return Err(ChainError::NoGenesis),
+            [single] => *single,
+            multiple => {
+                let mut ids: Vec<&str> = multiple.iter().map(|e| e.id.as_str()).collect();
+                ids.sort_unstable();
+                return Err(ChainError::MultipleGenesis {
+                    first: ids[0].to_string(),
+                    second: ids[1].to_string(),
+                });
+            }
+        };
+
+        let mut ordered = vec![genesis];
+        let mut current = genesis;
+        while let Some(children) = children_of.get(current.id.as_str()) {
+            current = children[0];
+            ordered.push(current);
+        }
+
+        // Every individual link can check out (no dangling predecessor, no fork, a single
+        // genesis) while a cycle disconnected from that genesis is still never walked above,
+        // e.g. two events that only reference each other as predecessors. Catch that here
+        // instead of silently reporting success on a partial chain.
+        if ordered.len() != events.len() {
+            let visited: std::collections::HashSet<&str> =
+                ordered.iter().map(|event| event.id.as_str()).collect();
+            let mut event_ids: Vec<String> = events
+                .iter()
+                .filter(|event| !visited.contains(event.id.as_str()))
+                .map(|event| event.id.clone())
+                .collect();
+            event_ids.sort_unstable();
+            return Err(ChainError::Disconnected { event_ids });
+        }
+
+        let mut ordered_ids = Vec::with_capacity(ordered.len());
+        let mut turns = Vec::with_capacity(ordered.len());
+        let mut previous_turn: Option<u64> = None;
+        for event in &ordered {
+            let turn = turn_of(event)?;
+            if previous_turn.is_some_and(|previous_turn| turn <= previous_turn) {
+                return Err(ChainError::NonIncreasingTurn {
+                    event_id: event.id.clone(),
+                    previous_turn: previous_turn.expect("is_some_and checked this is Some"),
+                });
+            }
+            previous_turn = Some(turn);
+            turns.push(turn);
+            ordered_ids.push(event.id.clone());
+        }
+
+        Ok(ChainReport {
+            ordered_ids,
+            missing_turns: missing_turns(&turns),
+        })
+    }
+}
+
+fn turn_of(event: &NostrEvent) -> Result<u64, ChainError> {
+    crate::event::find_tag(&event.tags, "turn")
+        .ok_or_else(|| ChainError::MissingTurnTag {
+            event_id: event.id.clone(),
+        })?
+        .parse()
+        .map_err(|_| ChainError::MalformedTurnTag {
+            event_id: event.id.clone(),
+        })
+}
+
+fn predecessor_of(event: &NostrEvent) -> Option<String> {
+    crate::event::find_tag(&event.tags, "e").map(str::to_string)
+}
+
+/// Turn numbers missing between consecutive entries of an already-sorted, gap-detected chain.
+fn missing_turns(turns: &[u64]) -> Vec<u64> {
+    turns
+        .windows(2)
+        .flat_map(|pair| (pair[0] + 1)..pair[1])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{Keypair, Secp256k1, SecretKey};
+
+    use crate::event::sign_action;
+    use crate::events::GameAction;
+
+    fn keypair() -> Keypair {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[0x07; 32]).expect("valid secret key");
+        Keypair::from_secret_key(&secp, &secret)
+    }
+
+    fn chain(turns: &[u64]) -> Vec<NostrEvent> {
+        let keypair = keypair();
+        let mut events = Vec::new();
+        let mut previous: Option<String> = None;
+        for &turn in turns {
+            let action = GameAction {
+                turn,
+                payload: vec![],
+            };
+            let event = sign_action(&action, &keypair, 1_700_000_000 + turn, previous.as_deref())
+                .expect("sign");
+            previous = Some(event.id.clone());
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn verifies_a_complete_chain_in_order() {
+        let events = chain(&[0, 1, 2, 3]);
+        let report = ChainVerifier::verify(&events).expect("verify");
+
+        assert_eq!(
+            report.ordered_ids,
+            events.iter().map(|e| e.id.clone()).collect::<Vec<_>>()
+        );
+        assert!(report.missing_turns.is_empty());
+    }
+
+    #[test]
+    fn reports_missing_turns_as_backfill_gaps() {
+        let events = chain(&[0, 3]);
+        let report = ChainVerifier::verify(&events).expect("verify");
+        assert_eq!(report.missing_turns, vec![1, 2]);
+    }
+
+    #[test]
+    fn detects_fork_when_two_events_claim_the_same_predecessor() {
+        let mut events = chain(&[0, 1]);
+        let keypair = keypair();
+        let genesis_id = events[0].id.clone();
+        let forked = sign_action(
+            &GameAction {
+                turn: 1,
+                payload: vec![9],
+            },
+            &keypair,
+            1_700_000_099,
+            Some(&genesis_id),
+        )
+        .expect("sign");
+        events.push(forked);
+
+        assert!(matches!(
+            ChainVerifier::verify(&events),
+            Err(ChainError::Fork { .. })
+        ));
+    }
+
+    #[test]
+    fn detects_dangling_predecessor() {
+        let mut events = chain(&[0]);
+        let keypair = keypair();
+        let orphan = sign_action(
+            &GameAction {
+                turn: 1,
+                payload: vec![],
+            },
+            &keypair,
+            1_700_000_001,
+            Some("not-a-real-event-id"),
+        )
+        .expect("sign");
+        events.push(orphan);
+
+        assert!(matches!(
+            ChainVerifier::verify(&events),
+            Err(ChainError::DanglingPredecessor { .. })
+        ));
+    }
+
+    #[test]
+    fn detects_cycle_disconnected_from_genesis() {
+        // Hash-linking can't actually produce a cycle through real signing (each event's id
+        // would have to be known before the other is signed), but nothing stops a malicious
+        // or buggy relay from serving two fabricated events whose `e` tags just point at each
+        // other; `ChainVerifier` only reads tags, so build them directly for this case.
+        let mut events = chain(&[0, 1]);
+
+        let cycle_a = NostrEvent {
+            id: "cycle-a".to_string(),
+            pubkey: "ab".repeat(32),
+            created_at: 1_700_000_100,
+            kind: crate::event::GAME_ACTION_KIND,
+            tags: vec![
+                vec!["turn".to_string(), "9".to_string()],
+                vec!["e".to_string(), "cycle-b".to_string()],
+            ],
+            content: String::new(),
+            sig: "00".repeat(64),
+        };
+        let cycle_b = NostrEvent {
+            id: "cycle-b".to_string(),
+            pubkey: "ab".repeat(32),
+            created_at: 1_700_000_101,
+            kind: crate::event::GAME_ACTION_KIND,
+            tags: vec![
+                vec!["turn".to_string(), "10".to_string()],
+                vec!["e".to_string(), "cycle-a".to_string()],
+            ],
+            content: String::new(),
+            sig: "00".repeat(64),
+        };
+        events.push(cycle_a);
+        events.push(cycle_b);
+
+        match ChainVerifier::verify(&events) {
+            Err(ChainError::Disconnected { event_ids }) => {
+                assert_eq!(event_ids, vec!["cycle-a".to_string(), "cycle-b".to_string()]);
+            }
+            other => panic!("expected Disconnected, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn detects_non_increasing_turn() {
+        let keypair = keypair();
+        let genesis = sign_action(
+            &GameAction {
+                turn: 5,
+                payload: vec![],
+            },
+            &keypair,
+            1_700_000_000,
+            None,
+        )
+        .expect("sign");
+        let regressed = sign_action(
+            &GameAction {
+                turn: 5,
+                payload: vec![],
+            },
+            &keypair,
+            1_700_000_001,
+            Some(&genesis.id),
+        )
+        .expect("sign");
+
+        assert!(matches!(
+            ChainVerifier::verify(&[genesis, regressed]),
+            Err(ChainError::NonIncreasingTurn { .. })
+        ));
+    }
+}
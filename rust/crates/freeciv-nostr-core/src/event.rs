@@ -0,0 +1,250 @@
+//! NIP-01 Nostr event construction and BIP-340 Schnorr signing for [`GameAction`]s.
+
+use secp256k1::schnorr::Signature;
+use secp256k1::{Keypair, Message, Secp256k1, XOnlyPublicKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::events::GameAction;
+
+/// Nostr event kind used for published game-turn actions.
+pub const GAME_ACTION_KIND: i32 = 31337;
+
+/// A fully-formed, signed Nostr event per NIP-01.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NostrEvent {
+    /// Lowercase hex SHA-256 of the serialized event, per NIP-01.
+    pub id: String,
+    /// Lowercase hex x-only secp256k1 public key of the signer.
+    pub pubkey: String,
+    /// Unix timestamp (seconds) at which the event was created.
+    pub created_at: u64,
+    /// Event kind; game actions use [`GAME_ACTION_KIND`].
+    pub kind: i32,
+    /// NIP-01 tags, e.g. `["turn", "42"]`.
+    pub tags: Vec<Vec<String>>,
+    /// Event content; hex-encoded [`GameAction::payload`] for game actions.
+    pub content: String,
+    /// Lowercase hex BIP-340 Schnorr signature over `id`.
+    pub sig: String,
+}
+
+/// Errors that can occur while signing a [`GameAction`] into a [`NostrEvent`].
+#[derive(Debug, thiserror::Error)]
+pub enum SignError {
+    /// The supplied key material was rejected by secp256k1.
+    #[error("invalid key material: {0}")]
+    InvalidKey(#[from] secp256k1::Error),
+}
+
+/// Errors that can occur while verifying a [`NostrEvent`].
+#[derive(Debug, thiserror::Error)]
+pub enum VerifyError {
+    /// The event's `id` field does not match the recomputed hash of its contents.
+    #[error("event id does not match computed id (tampered content)")]
+    IdMismatch,
+    /// The `id`, `pubkey`, or `sig` field was not valid hex.
+    #[error("malformed hex field: {0}")]
+    InvalidHex(#[from] hex::FromHexError),
+    /// The `pubkey` field was not a valid x-only secp256k1 public key.
+    #[error("invalid public key: {0}")]
+    InvalidPublicKey(secp256k1::Error),
+    /// The `sig` field was not a valid BIP-340 Schnorr signature encoding.
+    #[error("invalid signature encoding: {0}")]
+    InvalidSignature(secp256k1::Error),
+    /// The signature did not verify against `id` and `pubkey`.
+    #[error("schnorr signature verification failed")]
+    SignatureInvalid,
+}
+
+/// Sign `action` into a wire-valid NIP-01 Nostr event using `keypair`, timestamped at
+/// `created_at` (Unix seconds).
+///
+/// The turn number is carried as a `["turn", "<n>"]` tag and the action payload is
+/// hex-encoded into `content`. When `previous_event_id` is `Some`, an `["e", "<id>"]` tag
+/// links this event to the prior turn's event for the same game, forming a hash-linked
+/// chain; pass `None` only for a game's genesis event. The event is hashed and signed
+/// exactly as specified by NIP-01: `sha256([0, pubkey, created_at, kind, tags, content])`
+/// with no extra whitespace in the serialized array, signed with BIP-340 Schnorr.
+pub fn sign_action(
+    action: &GameAction,
+    keypair: &Keypair,
+    created_at: u64,
+    previous_event_id: Option<&str>,
+) -> Result<NostrEvent, SignError> {
+    let mut tags = vec![vec!["turn".to_string(), action.turn.to_string()]];
+    if let Some(previous_event_id) = previous_event_id {
+        tags.push(vec!["e".to_string(), previous_event_id.to_string()]);
+    }
+    let content = hex::encode(&action.payload);
+
+    Ok(finalize_and_sign(
+        keypair,
+        created_at,
+        GAME_ACTION_KIND,
+        tags,
+        content,
+    ))
+}
+
+/// Compute the NIP-01 id for `(pubkey, created_at, kind, tags, content)` and sign it with
+/// `keypair`, producing a complete [`NostrEvent`]. Shared by [`sign_action`] and
+/// [`crate::seal::seal_action`], which both need the same hash-then-sign procedure over
+/// different tags and content.
+pub(crate) fn finalize_and_sign(
+    keypair: &Keypair,
+    created_at: u64,
+    kind: i32,
+    tags: Vec<Vec<String>>,
+    content: String,
+) -> NostrEvent {
+    let secp = Secp256k1::signing_only();
+    let (xonly, _parity) = keypair.x_only_public_key();
+    let pubkey = hex::encode(xonly.serialize());
+
+    let id = compute_id(&pubkey, created_at, kind, &tags, &content);
+    let message = Message::from_digest(id);
+    let sig = secp.sign_schnorr(&message, keypair);
+
+    NostrEvent {
+        id: hex::encode(id),
+        pubkey,
+        created_at,
+        kind,
+        tags,
+        content,
+        sig: hex::encode(sig.as_ref()),
+    }
+}
+
+/// Recompute `event`'s id from its fields and verify its BIP-340 Schnorr signature.
+pub fn verify_event(event: &NostrEvent) -> Result<(), VerifyError> {
+    let expected_id = compute_id(
+        &event.pubkey,
+        event.created_at,
+        event.kind,
+        &event.tags,
+        &event.content,
+    );
+
+    let id_bytes = hex::decode(&event.id)?;
+    if id_bytes != expected_id {
+        return Err(VerifyError::IdMismatch);
+    }
+
+    let pubkey_bytes = hex::decode(&event.pubkey)?;
+    let xonly =
+        XOnlyPublicKey::from_slice(&pubkey_bytes).map_err(VerifyError::InvalidPublicKey)?;
+    let sig_bytes = hex::decode(&event.sig)?;
+    let sig = Signature::from_slice(&sig_bytes).map_err(VerifyError::InvalidSignature)?;
+    let message = Message::from_digest(expected_id);
+
+    Secp256k1::verification_only()
+        .verify_schnorr(&sig, &message, &xonly)
+        .map_err(|_| VerifyError::SignatureInvalid)
+}
+
+/// First value of the first tag named `name`, e.g. `find_tag(tags, "turn")` on
+/// `[["turn", "42"]]` returns `Some("42")`.
+pub(crate) fn find_tag<'a>(tags: &'a [Vec<String>], name: &str) -> Option<&'a str> {
+    tags.iter()
+        .find(|tag| tag.first().map(String::as_str) == Some(name))
+        .and_then(|tag| tag.get(1))
+        .map(String::as_str)
+}
+
+/// Compute the NIP-01 event id: `sha256(json([0, pubkey, created_at, kind, tags, content]))`.
+fn compute_id(
+    pubkey: &str,
+    created_at: u64,
+    kind: i32,
+    tags: &[Vec<String>],
+    content: &str,
+) -> [u8; 32] {
+    let array = Value::Array(vec![
+        Value::from(0),
+        Value::from(pubkey),
+        Value::from(created_at),
+        Value::from(kind),
+        serde_json::to_value(tags).expect("tags are valid JSON"),
+        Value::from(content),
+    ]);
+    let serialized = serde_json::to_string(&array).expect("NIP-01 array serializes");
+
+    let mut hasher = Sha256::new();
+    hasher.update(serialized.as_bytes());
+    hasher.finalize().into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_keypair() -> Keypair {
+        let secp = Secp256k1::new();
+        let secret = secp256k1::SecretKey::from_slice(&[0x42; 32]).expect("valid secret key");
+        Keypair::from_secret_key(&secp, &secret)
+    }
+
+    #[test]
+    fn sign_and_verify_round_trip() {
+        let action = GameAction {
+            turn: 42,
+            payload: vec![1, 2, 3, 4],
+        };
+        let event = sign_action(&action, &test_keypair(), 1_700_000_000, None).expect("sign");
+
+        assert_eq!(event.kind, GAME_ACTION_KIND);
+        assert_eq!(event.tags, vec![vec!["turn".to_string(), "42".to_string()]]);
+        assert_eq!(event.content, hex::encode([1, 2, 3, 4]));
+        verify_event(&event).expect("verify");
+    }
+
+    #[test]
+    fn tampered_content_fails_verification() {
+        let action = GameAction {
+            turn: 1,
+            payload: vec![0xaa],
+        };
+        let mut event = sign_action(&action, &test_keypair(), 1_700_000_000, None).expect("sign");
+        event.content = hex::encode([0xbb]);
+
+        assert!(matches!(
+            verify_event(&event),
+            Err(VerifyError::IdMismatch)
+        ));
+    }
+
+    #[test]
+    fn chained_event_carries_e_tag_to_predecessor() {
+        let action = GameAction {
+            turn: 2,
+            payload: vec![],
+        };
+        let event = sign_action(&action, &test_keypair(), 1_700_000_000, Some("deadbeef"))
+            .expect("sign");
+
+        assert!(event
+            .tags
+            .contains(&vec!["e".to_string(), "deadbeef".to_string()]));
+        verify_event(&event).expect("verify");
+    }
+
+    #[test]
+    fn tampered_signature_fails_verification() {
+        let action = GameAction {
+            turn: 1,
+            payload: vec![0xaa],
+        };
+        let mut event = sign_action(&action, &test_keypair(), 1_700_000_000, None).expect("sign");
+        let mut sig_bytes = hex::decode(&event.sig).unwrap();
+        sig_bytes[0] ^= 0xff;
+        event.sig = hex::encode(sig_bytes);
+
+        assert!(matches!(
+            verify_event(&event),
+            Err(VerifyError::SignatureInvalid)
+        ));
+    }
+}
@@ -0,0 +1,22 @@
+//! Core game-action types and Nostr event plumbing shared by the freeciv-nostr crates.
+
+pub mod chain;
+pub mod event;
+pub mod events;
+pub mod queue;
+pub mod relay_ws;
+pub mod seal;
+pub mod subscriber;
+
+pub use chain::{ChainError, ChainReport, ChainVerifier};
+pub use event::{sign_action, verify_event, NostrEvent, SignError, VerifyError, GAME_ACTION_KIND};
+pub use events::GameAction;
+pub use queue::{
+    DeadLetter, EnqueueError, EventQueue, QueueConfig, QueueConfigError, QueueMetrics,
+    RelayTransport,
+};
+pub use relay_ws::WebsocketRelay;
+pub use seal::{open_action, seal_action, OpenError, SealError, SEALED_ACTION_KIND};
+pub use subscriber::{
+    GameStateSubscriber, RelaySource, SubscriberConfig, SubscriberEvent, SubscriptionFilter,
+};
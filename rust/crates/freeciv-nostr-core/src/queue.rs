@@ -0,0 +1,342 @@
+//! Retrying multi-relay publish queue.
+//!
+//! Modeled on the activity-queue pattern used in federation delivery libraries: a single
+//! background worker accepts signed [`NostrEvent`]s and fans each one out concurrently to every
+//! configured relay. Each relay is retried independently with exponential backoff so one
+//! unreachable relay never blocks delivery to the healthy ones.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{mpsc, Mutex};
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+use crate::event::NostrEvent;
+
+/// A relay transport capable of delivering a single signed event to a single relay.
+///
+/// Implementations typically own (or lazily open) a websocket connection to `relay_url`.
+/// Tests substitute a fake transport to avoid real network I/O.
+#[async_trait::async_trait]
+pub trait RelayTransport: Send + Sync {
+    /// Publish `event` to `relay_url`, returning an error if the relay rejects or cannot be
+    /// reached.
+    async fn publish(&self, relay_url: &str, event: &NostrEvent) -> Result<(), RelayError>;
+}
+
+/// Error returned by a [`RelayTransport`] when an event could not be delivered.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("relay {relay} rejected event {event_id}: {reason}")]
+pub struct RelayError {
+    /// The relay URL that failed to accept the event.
+    pub relay: String,
+    /// The id of the event that failed to publish.
+    pub event_id: String,
+    /// Human-readable failure reason (transport error, relay NOTICE, etc.).
+    pub reason: String,
+}
+
+/// Backoff and retry policy for relay publishes.
+#[derive(Debug, Clone)]
+pub struct QueueConfig {
+    /// Relay URLs to fan out every enqueued event to.
+    pub relays: Vec<String>,
+    /// Maximum attempts per relay before an event is dead-lettered for that relay.
+    pub max_attempts: u32,
+    /// Backoff before the first retry; doubles on each subsequent attempt up to `max_backoff`.
+    pub initial_backoff: Duration,
+    /// Ceiling on the exponential backoff delay.
+    pub max_backoff: Duration,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            relays: Vec::new(),
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(32),
+        }
+    }
+}
+
+/// A relay delivery that exhausted all retry attempts.
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    /// The relay that never accepted the event.
+    pub relay: String,
+    /// The event that failed to publish.
+    pub event: NostrEvent,
+    /// The error from the final attempt.
+    pub last_error: RelayError,
+}
+
+/// Point-in-time counters for queue activity.
+#[derive(Debug, Default)]
+pub struct QueueMetrics {
+    in_flight: AtomicU64,
+    retries: AtomicU64,
+    dead_lettered: AtomicU64,
+}
+
+impl QueueMetrics {
+    /// Number of enqueued events currently being published.
+    pub fn in_flight(&self) -> u64 {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Total retry attempts issued across the queue's lifetime.
+    pub fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    /// Total relay deliveries that exhausted their retries and were dead-lettered.
+    pub fn dead_lettered(&self) -> u64 {
+        self.dead_lettered.load(Ordering::Relaxed)
+    }
+}
+
+/// Errors returned by [`EventQueue::enqueue`].
+#[derive(Debug, thiserror::Error)]
+pub enum EnqueueError {
+    /// The background worker has already shut down.
+    #[error("queue worker has stopped")]
+    WorkerStopped,
+}
+
+/// Errors returned by [`EventQueue::spawn`] for an invalid [`QueueConfig`].
+#[derive(Debug, thiserror::Error)]
+pub enum QueueConfigError {
+    /// `max_attempts` was zero, which would leave every relay delivery without a final error
+    /// to dead-letter.
+    #[error("QueueConfig::max_attempts must be at least 1, got 0")]
+    ZeroMaxAttempts,
+}
+
+/// Background publish queue that fans signed events out to every configured relay, retrying
+/// each relay independently with exponential backoff.
+pub struct EventQueue {
+    sender: mpsc::UnboundedSender<NostrEvent>,
+    worker: Option<JoinHandle<()>>,
+    metrics: Arc<QueueMetrics>,
+    dead_letters: Arc<Mutex<VecDeque<DeadLetter>>>,
+}
+
+impl EventQueue {
+    /// Spawn a new queue worker that fans events out to `config.relays` using `transport`.
+    ///
+    /// Returns [`QueueConfigError::ZeroMaxAttempts`] if `config.max_attempts` is `0`, since
+    /// `publish_with_retry` needs at least one attempt to have a final error to dead-letter.
+    pub fn spawn(
+        config: QueueConfig,
+        transport: Arc<dyn RelayTransport>,
+    ) -> Result<Self, QueueConfigError> {
+        if config.max_attempts == 0 {
+            return Err(QueueConfigError::ZeroMaxAttempts);
+        }
+
+        let (sender, mut receiver) = mpsc::unbounded_channel::<NostrEvent>();
+        let metrics = Arc::new(QueueMetrics::default());
+        let dead_letters = Arc::new(Mutex::new(VecDeque::new()));
+
+        let worker_metrics = Arc::clone(&metrics);
+        let worker_dead_letters = Arc::clone(&dead_letters);
+        let worker = tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                worker_metrics.in_flight.fetch_add(1, Ordering::Relaxed);
+
+                let deliveries = config.relays.iter().map(|relay| {
+                    publish_with_retry(
+                        relay.clone(),
+                        event.clone(),
+                        Arc::clone(&transport),
+                        &config,
+                        Arc::clone(&worker_metrics),
+                    )
+                });
+                for result in futures::future::join_all(deliveries).await {
+                    if let Err(dead_letter) = result {
+                        worker_metrics.dead_lettered.fetch_add(1, Ordering::Relaxed);
+                        worker_dead_letters.lock().await.push_back(dead_letter);
+                    }
+                }
+
+                worker_metrics.in_flight.fetch_sub(1, Ordering::Relaxed);
+            }
+        });
+
+        Ok(Self {
+            sender,
+            worker: Some(worker),
+            metrics,
+            dead_letters,
+        })
+    }
+
+    /// Queue `event` for delivery to every configured relay. Returns immediately; delivery
+    /// happens on the background worker.
+    pub fn enqueue(&self, event: NostrEvent) -> Result<(), EnqueueError> {
+        self.sender
+            .send(event)
+            .map_err(|_| EnqueueError::WorkerStopped)
+    }
+
+    /// Current queue metrics (in-flight events, retry count, dead-lettered count).
+    pub fn metrics(&self) -> Arc<QueueMetrics> {
+        Arc::clone(&self.metrics)
+    }
+
+    /// Take all dead letters accumulated so far, clearing the backing store.
+    pub async fn drain_dead_letters(&self) -> Vec<DeadLetter> {
+        self.dead_letters.lock().await.drain(..).collect()
+    }
+
+    /// Stop accepting new events and wait for all in-flight deliveries to finish.
+    pub async fn shutdown(mut self) {
+        drop(self.sender);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.await;
+        }
+    }
+}
+
+async fn publish_with_retry(
+    relay: String,
+    event: NostrEvent,
+    transport: Arc<dyn RelayTransport>,
+    config: &QueueConfig,
+    metrics: Arc<QueueMetrics>,
+) -> Result<(), DeadLetter> {
+    let mut backoff = config.initial_backoff;
+    let mut last_error = None;
+
+    for attempt in 1..=config.max_attempts {
+        match transport.publish(&relay, &event).await {
+            Ok(()) => return Ok(()),
+            Err(err) => {
+                last_error = Some(err);
+                if attempt < config.max_attempts {
+                    metrics.retries.fetch_add(1, Ordering::Relaxed);
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(config.max_backoff);
+                }
+            }
+        }
+    }
+
+    Err(DeadLetter {
+        relay,
+        event,
+        last_error: last_error.expect("max_attempts is at least 1"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex as StdMutex;
+
+    fn test_event(turn: u64) -> NostrEvent {
+        NostrEvent {
+            id: format!("id-{turn}"),
+            pubkey: "ab".repeat(32),
+            created_at: 1_700_000_000,
+            kind: crate::event::GAME_ACTION_KIND,
+            tags: vec![vec!["turn".to_string(), turn.to_string()]],
+            content: String::new(),
+            sig: "00".repeat(64),
+        }
+    }
+
+    /// Fails the configured relays the configured number of times before succeeding.
+    struct ScriptedTransport {
+        remaining_failures: StdMutex<std::collections::HashMap<String, u32>>,
+    }
+
+    #[async_trait::async_trait]
+    impl RelayTransport for ScriptedTransport {
+        async fn publish(&self, relay_url: &str, event: &NostrEvent) -> Result<(), RelayError> {
+            let mut remaining = self.remaining_failures.lock().unwrap();
+            let count = remaining.entry(relay_url.to_string()).or_insert(0);
+            if *count > 0 {
+                *count -= 1;
+                return Err(RelayError {
+                    relay: relay_url.to_string(),
+                    event_id: event.id.clone(),
+                    reason: "simulated failure".to_string(),
+                });
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn unhealthy_relay_does_not_block_healthy_ones() {
+        let mut remaining_failures = std::collections::HashMap::new();
+        remaining_failures.insert("wss://flaky".to_string(), 100);
+        let transport = Arc::new(ScriptedTransport {
+            remaining_failures: StdMutex::new(remaining_failures),
+        });
+
+        let config = QueueConfig {
+            relays: vec!["wss://healthy".to_string(), "wss://flaky".to_string()],
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let queue = EventQueue::spawn(config, transport).expect("valid config");
+        let metrics = queue.metrics();
+        queue.enqueue(test_event(1)).expect("enqueue");
+        queue.shutdown().await;
+
+        // Only the flaky relay exhausts its two attempts; the healthy one succeeds first try.
+        assert_eq!(metrics.dead_lettered(), 1);
+        assert_eq!(metrics.retries(), 1);
+    }
+
+    #[tokio::test]
+    async fn exhausted_retries_are_dead_lettered() {
+        let mut remaining_failures = std::collections::HashMap::new();
+        remaining_failures.insert("wss://always-down".to_string(), u32::MAX);
+        let transport = Arc::new(ScriptedTransport {
+            remaining_failures: StdMutex::new(remaining_failures),
+        });
+
+        let config = QueueConfig {
+            relays: vec!["wss://always-down".to_string()],
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        };
+        let metrics = {
+            let queue = EventQueue::spawn(config, transport).expect("valid config");
+            let metrics = queue.metrics();
+            queue.enqueue(test_event(7)).expect("enqueue");
+            queue.shutdown().await;
+            metrics
+        };
+
+        assert_eq!(metrics.dead_lettered(), 1);
+        assert_eq!(metrics.retries(), 2);
+    }
+
+    #[tokio::test]
+    async fn spawn_rejects_zero_max_attempts() {
+        let transport = Arc::new(ScriptedTransport {
+            remaining_failures: StdMutex::new(std::collections::HashMap::new()),
+        });
+        let config = QueueConfig {
+            relays: vec!["wss://relay".to_string()],
+            max_attempts: 0,
+            ..QueueConfig::default()
+        };
+
+        assert!(matches!(
+            EventQueue::spawn(config, transport),
+            Err(QueueConfigError::ZeroMaxAttempts)
+        ));
+    }
+}
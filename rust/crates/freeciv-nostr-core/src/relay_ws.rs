@@ -0,0 +1,182 @@
+//! Websocket-based [`RelayTransport`]/[`RelaySource`] implementation for talking to real
+//! Nostr relays, per NIP-01's `["EVENT", ...]` and `["REQ", ...]` client messages.
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+use tokio_tungstenite::MaybeTlsStream;
+
+use crate::event::NostrEvent;
+use crate::queue::{RelayError, RelayTransport};
+use crate::subscriber::{RelaySource, SubscriptionFilter};
+
+/// The subscription id used for the single, crate-internal game-state subscription opened
+/// per relay.
+const SUBSCRIPTION_ID: &str = "freeciv-nostr";
+
+/// How long [`WebsocketRelay::publish`] waits for the relay's `["OK", ...]` response before
+/// treating the publish as failed.
+const PUBLISH_ACK_TIMEOUT: Duration = Duration::from_secs(10);
+
+type WsStream = tokio_tungstenite::WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Publishes and subscribes to relays by opening a websocket connection per call. Stateless
+/// and cheap to share behind an `Arc`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct WebsocketRelay;
+
+#[async_trait]
+impl RelayTransport for WebsocketRelay {
+    async fn publish(&self, relay_url: &str, event: &NostrEvent) -> Result<(), RelayError> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(relay_url)
+            .await
+            .map_err(|err| connection_error(relay_url, &event.id, err))?;
+
+        let message = json!(["EVENT", event]).to_string();
+        socket
+            .send(WsMessage::Text(message))
+            .await
+            .map_err(|err| send_error(relay_url, &event.id, err))?;
+
+        // Per NIP-01, sending the frame only queues the event; the relay can still reject it
+        // (policy, rate limit, bad signature) via an `["OK", id, accepted, reason]` reply. Wait
+        // for that reply instead of reporting success as soon as the bytes are on the wire.
+        let ack = tokio::time::timeout(
+            PUBLISH_ACK_TIMEOUT,
+            wait_for_ok(&mut socket, relay_url, &event.id),
+        )
+        .await
+            .map_err(|_| RelayError {
+                relay: relay_url.to_string(),
+                event_id: event.id.clone(),
+                reason: "timed out waiting for OK response".to_string(),
+            })??;
+
+        if !ack.accepted {
+            return Err(RelayError {
+                relay: relay_url.to_string(),
+                event_id: event.id.clone(),
+                reason: ack.message,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The relay's parsed `["OK", id, accepted, message]` reply.
+struct OkAck {
+    accepted: bool,
+    message: String,
+}
+
+/// Read frames from `socket` until the `["OK", ...]` reply for `event_id` arrives, ignoring
+/// anything else (`NOTICE`s, `OK`s for other events, etc.).
+async fn wait_for_ok(
+    socket: &mut WsStream,
+    relay_url: &str,
+    event_id: &str,
+) -> Result<OkAck, RelayError> {
+    loop {
+        let message = socket.next().await.ok_or_else(|| RelayError {
+            relay: relay_url.to_string(),
+            event_id: event_id.to_string(),
+            reason: "relay closed the connection before sending OK".to_string(),
+        })?;
+        let message = message.map_err(|err| send_error(relay_url, event_id, err))?;
+        let WsMessage::Text(text) = message else {
+            continue;
+        };
+        let Ok(frame) = serde_json::from_str::<Value>(&text) else {
+            continue;
+        };
+        if frame.get(0).and_then(Value::as_str) != Some("OK") {
+            continue;
+        }
+        if frame.get(1).and_then(Value::as_str) != Some(event_id) {
+            continue;
+        }
+        let accepted = frame.get(2).and_then(Value::as_bool).unwrap_or(false);
+        let message = frame
+            .get(3)
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string();
+        return Ok(OkAck { accepted, message });
+    }
+}
+
+#[async_trait]
+impl RelaySource for WebsocketRelay {
+    async fn subscribe(
+        &self,
+        relay_url: &str,
+        filter: &SubscriptionFilter,
+        sink: mpsc::UnboundedSender<NostrEvent>,
+    ) -> Result<(), RelayError> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(relay_url)
+            .await
+            .map_err(|err| connection_error(relay_url, "", err))?;
+
+        let mut nostr_filter = json!({ "kinds": [filter.kind] });
+        if !filter.authors.is_empty() {
+            nostr_filter["authors"] = json!(filter.authors);
+        }
+        let request = json!(["REQ", SUBSCRIPTION_ID, nostr_filter]).to_string();
+        socket
+            .send(WsMessage::Text(request))
+            .await
+            .map_err(|err| send_error(relay_url, "", err))?;
+
+        while let Some(message) = socket.next().await {
+            let message = message.map_err(|err| send_error(relay_url, "", err))?;
+            let WsMessage::Text(text) = message else {
+                continue;
+            };
+            let Ok(frame) = serde_json::from_str::<Value>(&text) else {
+                continue;
+            };
+            if frame.get(0).and_then(Value::as_str) != Some("EVENT") {
+                continue;
+            }
+            let Some(event) = frame
+                .get(2)
+                .and_then(|value| serde_json::from_value::<NostrEvent>(value.clone()).ok())
+            else {
+                continue;
+            };
+            if sink.send(event).is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn connection_error(
+    relay_url: &str,
+    event_id: &str,
+    err: tokio_tungstenite::tungstenite::Error,
+) -> RelayError {
+    RelayError {
+        relay: relay_url.to_string(),
+        event_id: event_id.to_string(),
+        reason: format!("failed to connect: {err}"),
+    }
+}
+
+fn send_error(
+    relay_url: &str,
+    event_id: &str,
+    err: tokio_tungstenite::tungstenite::Error,
+) -> RelayError {
+    RelayError {
+        relay: relay_url.to_string(),
+        event_id: event_id.to_string(),
+        reason: format!("websocket error: {err}"),
+    }
+}
@@ -0,0 +1,325 @@
+//! Encrypted private game actions (diplomacy offers, secret orders) via a NIP-44-style scheme.
+//!
+//! Some game actions must be readable only by one player, not every relay subscriber.
+//! [`seal_action`] derives a shared secret via ECDH over secp256k1, keys a NIP-44-style
+//! scheme with it (ChaCha20 for confidentiality, HMAC-SHA256 for integrity over versioned,
+//! length-padded plaintext), and signs the result into a [`NostrEvent`] whose `content` holds
+//! the base64 ciphertext and whose `p` tag names the recipient. [`open_action`] reverses the
+//! process, surfacing any authentication failure as a typed [`OpenError`].
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chacha20::cipher::{KeyIvInit, StreamCipher};
+use chacha20::ChaCha20;
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use secp256k1::{Keypair, Parity, PublicKey, Secp256k1, SecretKey, XOnlyPublicKey};
+use sha2::{Digest, Sha256};
+
+use crate::event::{find_tag, finalize_and_sign, NostrEvent};
+use crate::events::GameAction;
+
+/// Nostr event kind used for sealed (encrypted) game actions.
+pub const SEALED_ACTION_KIND: i32 = 31338;
+
+const NIP44_VERSION: u8 = 2;
+const NONCE_LEN: usize = 12;
+const MAC_LEN: usize = 32;
+
+/// Errors that can occur while sealing a [`GameAction`] for a recipient.
+#[derive(Debug, thiserror::Error)]
+pub enum SealError {
+    /// The supplied key material was rejected by secp256k1.
+    #[error("invalid key material: {0}")]
+    InvalidKey(#[from] secp256k1::Error),
+}
+
+/// Errors that can occur while opening a sealed [`NostrEvent`].
+#[derive(Debug, thiserror::Error)]
+pub enum OpenError {
+    /// The decoded payload was too short to contain a version, nonce, and MAC.
+    #[error("sealed payload is truncated")]
+    Truncated,
+    /// The payload's version byte is not one this crate understands.
+    #[error("unsupported seal version {0}")]
+    UnsupportedVersion(u8),
+    /// The HMAC did not match; the payload was tampered with or opened with the wrong key.
+    #[error("authentication failed")]
+    AuthenticationFailed,
+    /// The decrypted, unpadded plaintext was malformed, or the event's `pubkey` was not hex or
+    /// its `turn` tag was missing or not a number.
+    #[error("decrypted plaintext is malformed")]
+    MalformedPlaintext,
+    /// The `content` field was not valid base64.
+    #[error("content is not valid base64: {0}")]
+    InvalidBase64(#[from] base64::DecodeError),
+    /// The supplied key material was rejected by secp256k1.
+    #[error("invalid key material: {0}")]
+    InvalidKey(#[from] secp256k1::Error),
+}
+
+/// Seal `action` so only the holder of `recipient_pk`'s secret key can read it, signing the
+/// result with `sender_sk`.
+///
+/// The turn number stays visible in a `["turn", "<n>"]` tag, as with public actions, so chain
+/// ordering still works even for sealed events. The recipient is named in a
+/// `["p", "<hex-pubkey>"]` tag. The action payload is encrypted and placed in `content` as
+/// base64.
+pub fn seal_action(
+    action: &GameAction,
+    recipient_pk: &XOnlyPublicKey,
+    sender_sk: &SecretKey,
+) -> Result<NostrEvent, SealError> {
+    let secp = Secp256k1::new();
+    let sender_keypair = Keypair::from_secret_key(&secp, sender_sk);
+    let recipient_full_pk = recipient_pk.public_key(Parity::Even);
+
+    let key = conversation_key(&recipient_full_pk, sender_sk);
+    let mut nonce = [0u8; NONCE_LEN];
+    rand::thread_rng().fill_bytes(&mut nonce);
+
+    let mut ciphertext = pad(&action.payload);
+    ChaCha20::new(&key.into(), &nonce.into()).apply_keystream(&mut ciphertext);
+
+    let payload = assemble_payload(&nonce, &ciphertext, &mac_tag(&key, &nonce, &ciphertext));
+    let content = BASE64.encode(payload);
+
+    let tags = vec![
+        vec!["turn".to_string(), action.turn.to_string()],
+        vec!["p".to_string(), hex::encode(recipient_pk.serialize())],
+    ];
+
+    Ok(finalize_and_sign(
+        &sender_keypair,
+        unix_now(),
+        SEALED_ACTION_KIND,
+        tags,
+        content,
+    ))
+}
+
+/// Current Unix time in seconds, for the `created_at` field of a freshly sealed event.
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock is after the Unix epoch")
+        .as_secs()
+}
+
+/// Open a sealed `event` with `recipient_sk`, returning the decrypted [`GameAction`].
+///
+/// The turn number is read back from the event's visible `turn` tag; only the payload was
+/// ever secret.
+pub fn open_action(event: &NostrEvent, recipient_sk: &SecretKey) -> Result<GameAction, OpenError> {
+    let sender_pubkey_bytes =
+        hex::decode(&event.pubkey).map_err(|_| OpenError::MalformedPlaintext)?;
+    let sender_xonly =
+        XOnlyPublicKey::from_slice(&sender_pubkey_bytes).map_err(OpenError::InvalidKey)?;
+    let sender_full_pk = sender_xonly.public_key(Parity::Even);
+
+    let key = conversation_key(&sender_full_pk, recipient_sk);
+    let payload = BASE64.decode(&event.content)?;
+    if payload.len() < 1 + NONCE_LEN + MAC_LEN {
+        return Err(OpenError::Truncated);
+    }
+
+    let version = payload[0];
+    if version != NIP44_VERSION {
+        return Err(OpenError::UnsupportedVersion(version));
+    }
+    let nonce = &payload[1..1 + NONCE_LEN];
+    let ciphertext = &payload[1 + NONCE_LEN..payload.len() - MAC_LEN];
+    let received_mac = &payload[payload.len() - MAC_LEN..];
+
+    let expected_mac = mac_tag(&key, nonce, ciphertext);
+    if !constant_time_eq(&expected_mac, received_mac) {
+        return Err(OpenError::AuthenticationFailed);
+    }
+
+    let mut plaintext = ciphertext.to_vec();
+    ChaCha20::new(&key.into(), nonce.into()).apply_keystream(&mut plaintext);
+    let payload = unpad(&plaintext).ok_or(OpenError::MalformedPlaintext)?;
+
+    let turn = find_tag(&event.tags, "turn")
+        .and_then(|turn| turn.parse().ok())
+        .ok_or(OpenError::MalformedPlaintext)?;
+
+    Ok(GameAction {
+        turn,
+        payload: payload.to_vec(),
+    })
+}
+
+/// Derive the NIP-44-style conversation key for `(their_pk, our_sk)` via ECDH: the shared
+/// secret's x-coordinate run through SHA-256.
+///
+/// `their_pk` is reconstructed from a 32-byte x-only key, which does not record which of the
+/// two possible y-parities the real point had. [`secp256k1::ecdh::SharedSecret::new`] hashes
+/// the full compressed point (including a parity byte), so using it here would make the
+/// derived key depend on whether the guessed parity happened to match — the two sides of a
+/// seal/open pair would agree only when sender and recipient keys happened to share the same
+/// real parity, about half the time. Multiplying by a point and its negation yields points
+/// that are themselves negations of each other, which always share the same x-coordinate, so
+/// hashing only the x-coordinate of the shared point (via [`secp256k1::ecdh::shared_secret_point`])
+/// is parity-agnostic and gives both sides the same key regardless of the guess.
+fn conversation_key(their_pk: &PublicKey, our_sk: &SecretKey) -> [u8; 32] {
+    let shared = secp256k1::ecdh::shared_secret_point(their_pk, our_sk);
+    let mut hasher = Sha256::new();
+    hasher.update(&shared[..32]);
+    hasher.finalize().into()
+}
+
+fn mac_tag(key: &[u8; 32], nonce: &[u8], ciphertext: &[u8]) -> [u8; MAC_LEN] {
+    let mut mac =
+        <Hmac<Sha256>>::new_from_slice(key).expect("any key length is valid for HMAC-SHA256");
+    mac.update(nonce);
+    mac.update(ciphertext);
+    mac.finalize().into_bytes().into()
+}
+
+fn assemble_payload(nonce: &[u8], ciphertext: &[u8], mac: &[u8; MAC_LEN]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(1 + nonce.len() + ciphertext.len() + MAC_LEN);
+    payload.push(NIP44_VERSION);
+    payload.extend_from_slice(nonce);
+    payload.extend_from_slice(ciphertext);
+    payload.extend_from_slice(mac);
+    payload
+}
+
+/// Length-prefix `plaintext` (u16 BE) and zero-pad it out to the next NIP-44-style bucket so
+/// ciphertext length leaks less about the true message size.
+fn pad(plaintext: &[u8]) -> Vec<u8> {
+    let mut framed = Vec::with_capacity(2 + plaintext.len());
+    framed.extend_from_slice(&(plaintext.len() as u16).to_be_bytes());
+    framed.extend_from_slice(plaintext);
+
+    let padded_len = padded_len(framed.len());
+    framed.resize(padded_len, 0);
+    framed
+}
+
+/// Reverse of [`pad`]: strip zero padding using the embedded length prefix.
+fn unpad(framed: &[u8]) -> Option<&[u8]> {
+    if framed.len() < 2 {
+        return None;
+    }
+    let len = u16::from_be_bytes([framed[0], framed[1]]) as usize;
+    framed.get(2..2 + len)
+}
+
+/// Round `unpadded_len` up to a bucket size that grows with the message, per NIP-44's padding
+/// scheme: small messages round up to 32-byte buckets, larger ones to coarser buckets.
+fn padded_len(unpadded_len: usize) -> usize {
+    if unpadded_len <= 32 {
+        return 32;
+    }
+    let next_power = 1usize << (usize::BITS - (unpadded_len - 1).leading_zeros());
+    let chunk = if next_power <= 256 { 32 } else { next_power / 8 };
+    ((unpadded_len - 1) / chunk + 1) * chunk
+}
+
+/// Constant-time byte comparison so MAC checking doesn't leak timing information.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+        == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys() -> (SecretKey, XOnlyPublicKey, SecretKey, XOnlyPublicKey) {
+        let secp = Secp256k1::new();
+        let sender_sk = SecretKey::from_slice(&[0x21; 32]).expect("valid secret key");
+        let sender_kp = Keypair::from_secret_key(&secp, &sender_sk);
+        let (sender_pk, _) = sender_kp.x_only_public_key();
+
+        let recipient_sk = SecretKey::from_slice(&[0x22; 32]).expect("valid secret key");
+        let recipient_kp = Keypair::from_secret_key(&secp, &recipient_sk);
+        let (recipient_pk, _) = recipient_kp.x_only_public_key();
+
+        (sender_sk, sender_pk, recipient_sk, recipient_pk)
+    }
+
+    #[test]
+    fn seal_and_open_round_trip() {
+        let (sender_sk, _sender_pk, recipient_sk, recipient_pk) = keys();
+        let action = GameAction {
+            turn: 9,
+            payload: b"offer: ceasefire".to_vec(),
+        };
+
+        let event = seal_action(&action, &recipient_pk, &sender_sk).expect("seal");
+        assert!(event
+            .tags
+            .contains(&vec!["p".to_string(), hex::encode(recipient_pk.serialize())]));
+
+        let opened = open_action(&event, &recipient_sk).expect("open");
+        assert_eq!(opened, action);
+    }
+
+    #[test]
+    fn seal_and_open_round_trip_with_mismatched_key_parity() {
+        // `[1; 32]` and `[2; 32]` land on opposite y-parities, which previously made
+        // `conversation_key` diverge between sealer and opener about half the time.
+        let secp = Secp256k1::new();
+        let sender_sk = SecretKey::from_slice(&[1; 32]).expect("valid secret key");
+        let sender_kp = Keypair::from_secret_key(&secp, &sender_sk);
+        let (_sender_pk, sender_parity) = sender_kp.x_only_public_key();
+
+        let recipient_sk = SecretKey::from_slice(&[2; 32]).expect("valid secret key");
+        let recipient_kp = Keypair::from_secret_key(&secp, &recipient_sk);
+        let (recipient_pk, recipient_parity) = recipient_kp.x_only_public_key();
+
+        assert_ne!(sender_parity, recipient_parity, "fixture should mix parities");
+
+        let action = GameAction {
+            turn: 3,
+            payload: b"mismatched parity round trip".to_vec(),
+        };
+        let event = seal_action(&action, &recipient_pk, &sender_sk).expect("seal");
+        let opened = open_action(&event, &recipient_sk).expect("open");
+        assert_eq!(opened, action);
+    }
+
+    #[test]
+    fn wrong_recipient_key_fails_authentication() {
+        let (sender_sk, _sender_pk, _recipient_sk, recipient_pk) = keys();
+        let action = GameAction {
+            turn: 1,
+            payload: b"secret orders".to_vec(),
+        };
+        let event = seal_action(&action, &recipient_pk, &sender_sk).expect("seal");
+
+        let wrong_sk = SecretKey::from_slice(&[0x99; 32]).expect("valid secret key");
+        assert!(matches!(
+            open_action(&event, &wrong_sk),
+            Err(OpenError::AuthenticationFailed)
+        ));
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails_authentication() {
+        let (sender_sk, _sender_pk, recipient_sk, recipient_pk) = keys();
+        let action = GameAction {
+            turn: 1,
+            payload: b"secret orders".to_vec(),
+        };
+        let mut event = seal_action(&action, &recipient_pk, &sender_sk).expect("seal");
+
+        let mut payload = BASE64.decode(&event.content).unwrap();
+        let last = payload.len() - MAC_LEN - 1;
+        payload[last] ^= 0xff;
+        event.content = BASE64.encode(payload);
+
+        assert!(matches!(
+            open_action(&event, &recipient_sk),
+            Err(OpenError::AuthenticationFailed)
+        ));
+    }
+}
@@ -0,0 +1,434 @@
+//! Relay subscriber that reconstructs ordered game state.
+//!
+//! This is the read-side complement to [`crate::queue::EventQueue`]: it opens subscriptions
+//! against one or more relays, verifies every incoming event's signature and chain linkage,
+//! de-duplicates by event id, buffers out-of-order arrivals, and emits a gap-free ordered
+//! sequence of [`GameAction`]s as turns become complete. A late-joining client or spectator
+//! can rebuild a match purely from relay state by draining this subscriber.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+
+use crate::event::{find_tag, verify_event, NostrEvent};
+use crate::events::GameAction;
+
+/// Selects which events a relay subscription should deliver.
+#[derive(Debug, Clone)]
+pub struct SubscriptionFilter {
+    /// Event kind to subscribe to, e.g. [`crate::event::GAME_ACTION_KIND`].
+    pub kind: i32,
+    /// Hex pubkeys of players whose actions should be delivered; empty means all authors.
+    pub authors: Vec<String>,
+}
+
+/// A relay connection capable of streaming matching events to a sink until it disconnects.
+///
+/// Implementations typically open a websocket to `relay_url`, send a NIP-01 `REQ`, and
+/// forward each `EVENT` message into `sink` for as long as the connection stays open. Tests
+/// substitute a fake source to avoid real network I/O.
+#[async_trait::async_trait]
+pub trait RelaySource: Send + Sync {
+    /// Subscribe to `relay_url` with `filter`, forwarding every event received to `sink`.
+    /// Returns (with `Ok` or `Err`) only once the subscription ends, so the caller can decide
+    /// whether and when to resubscribe.
+    async fn subscribe(
+        &self,
+        relay_url: &str,
+        filter: &SubscriptionFilter,
+        sink: mpsc::UnboundedSender<NostrEvent>,
+    ) -> Result<(), crate::queue::RelayError>;
+}
+
+/// An item emitted by [`GameStateSubscriber`] as the reconstructed game state advances.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriberEvent {
+    /// The next gap-free action in turn order; safe to apply to game state immediately.
+    Action(GameAction),
+    /// No event extending the chain past `after_turn` arrived within the reorder window;
+    /// a late-joining client should backfill starting at `after_turn + 1`.
+    Gap {
+        /// The last turn successfully emitted before the gap, or `0` if no action has been
+        /// emitted yet.
+        after_turn: u64,
+    },
+    /// An event with no `e` tag (a genesis) arrived after a genesis event was already accepted
+    /// for this subscription, and was evicted rather than buffered forever. This can happen
+    /// for a duplicate or forged genesis, or two games' events mixed on one subscription.
+    Rejected {
+        /// Id of the discarded event.
+        event_id: String,
+    },
+}
+
+/// Backoff and reordering policy for a [`GameStateSubscriber`].
+#[derive(Debug, Clone)]
+pub struct SubscriberConfig {
+    /// Relay URLs to subscribe to.
+    pub relays: Vec<String>,
+    /// Event filter applied to every relay subscription.
+    pub filter: SubscriptionFilter,
+    /// How long an out-of-order event waits for its predecessor before being reported as a gap.
+    pub reorder_window: Duration,
+    /// Backoff before the first resubscribe attempt after a relay disconnects.
+    pub initial_reconnect_backoff: Duration,
+    /// Ceiling on the exponential resubscribe backoff delay.
+    pub max_reconnect_backoff: Duration,
+}
+
+/// Subscribes to one or more relays and reconstructs a gap-free, ordered stream of
+/// [`GameAction`]s for a single game.
+pub struct GameStateSubscriber {
+    events: mpsc::UnboundedReceiver<SubscriberEvent>,
+    relay_workers: Vec<JoinHandle<()>>,
+    reorder_worker: JoinHandle<()>,
+}
+
+impl GameStateSubscriber {
+    /// Spawn subscriptions to every relay in `config.relays` using `source`, reconstructing
+    /// ordered game state as events arrive.
+    pub fn spawn(config: SubscriberConfig, source: Arc<dyn RelaySource>) -> Self {
+        let (raw_tx, raw_rx) = mpsc::unbounded_channel::<NostrEvent>();
+        let (out_tx, out_rx) = mpsc::unbounded_channel::<SubscriberEvent>();
+
+        let relay_workers = config
+            .relays
+            .iter()
+            .cloned()
+            .map(|relay| {
+                spawn_relay_worker(
+                    relay,
+                    config.filter.clone(),
+                    Arc::clone(&source),
+                    raw_tx.clone(),
+                    config.initial_reconnect_backoff,
+                    config.max_reconnect_backoff,
+                )
+            })
+            .collect();
+        drop(raw_tx);
+
+        let reorder_worker = spawn_reorder_worker(raw_rx, out_tx, config.reorder_window);
+
+        Self {
+            events: out_rx,
+            relay_workers,
+            reorder_worker,
+        }
+    }
+
+    /// Receive the next reconstructed action or gap notice, in order.
+    pub async fn recv(&mut self) -> Option<SubscriberEvent> {
+        self.events.recv().await
+    }
+
+    /// Non-blocking variant of [`Self::recv`] for callers (such as the C FFI) that poll
+    /// instead of awaiting. Returns `None` if nothing is available right now.
+    pub fn try_recv(&mut self) -> Option<SubscriberEvent> {
+        self.events.try_recv().ok()
+    }
+
+    /// Stop all relay subscriptions and the reordering worker.
+    pub fn shutdown(self) {
+        for worker in self.relay_workers {
+            worker.abort();
+        }
+        self.reorder_worker.abort();
+    }
+}
+
+fn spawn_relay_worker(
+    relay: String,
+    filter: SubscriptionFilter,
+    source: Arc<dyn RelaySource>,
+    sink: mpsc::UnboundedSender<NostrEvent>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut backoff = initial_backoff;
+        loop {
+            match source.subscribe(&relay, &filter, sink.clone()).await {
+                Ok(()) => backoff = initial_backoff,
+                Err(_) => {
+                    sleep(backoff).await;
+                    backoff = (backoff * 2).min(max_backoff);
+                    continue;
+                }
+            }
+            sleep(initial_backoff).await;
+        }
+    })
+}
+
+fn spawn_reorder_worker(
+    mut raw_rx: mpsc::UnboundedReceiver<NostrEvent>,
+    out_tx: mpsc::UnboundedSender<SubscriberEvent>,
+    reorder_window: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut buffer = ReorderBuffer::new(reorder_window);
+        let mut sweep = tokio::time::interval(reorder_window);
+        loop {
+            tokio::select! {
+                maybe_event = raw_rx.recv() => {
+                    let Some(event) = maybe_event else { break };
+                    for emitted in buffer.ingest(event) {
+                        if out_tx.send(emitted).is_err() {
+                            return;
+                        }
+                    }
+                }
+                _ = sweep.tick() => {
+                    for emitted in buffer.sweep_timeouts() {
+                        if out_tx.send(emitted).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// De-duplicates, verifies, and reorders raw events into a gap-free [`GameAction`] sequence.
+struct ReorderBuffer {
+    reorder_window: Duration,
+    tip: Option<String>,
+    tip_turn: Option<u64>,
+    emitted_ids: HashSet<String>,
+    known: HashMap<String, (NostrEvent, Instant)>,
+    children_of: HashMap<String, Vec<String>>,
+    pending_genesis: Vec<String>,
+    last_reported_gap_turn: Option<u64>,
+}
+
+impl ReorderBuffer {
+    fn new(reorder_window: Duration) -> Self {
+        Self {
+            reorder_window,
+            tip: None,
+            tip_turn: None,
+            emitted_ids: HashSet::new(),
+            known: HashMap::new(),
+            children_of: HashMap::new(),
+            pending_genesis: Vec::new(),
+            last_reported_gap_turn: None,
+        }
+    }
+
+    fn ingest(&mut self, event: NostrEvent) -> Vec<SubscriberEvent> {
+        if self.emitted_ids.contains(&event.id) || self.known.contains_key(&event.id) {
+            return Vec::new();
+        }
+        if verify_event(&event).is_err() {
+            return Vec::new();
+        }
+
+        let id = event.id.clone();
+        match find_tag(&event.tags, "e").map(str::to_string) {
+            Some(predecessor) => self.children_of.entry(predecessor).or_default().push(id.clone()),
+            None => self.pending_genesis.push(id.clone()),
+        }
+        self.known.insert(id, (event, Instant::now()));
+
+        self.drain()
+    }
+
+    fn drain(&mut self) -> Vec<SubscriberEvent> {
+        let mut emitted = Vec::new();
+
+        if !self.pending_genesis.is_empty() {
+            if self.tip.is_none() {
+                let genesis_id = self.pending_genesis.remove(0);
+                if let Some(action) = self.emit(&genesis_id) {
+                    emitted.push(SubscriberEvent::Action(action));
+                }
+            }
+            // Anything still in `pending_genesis` has no predecessor but arrived after a
+            // genesis was already accepted (or arrived alongside one that just failed to
+            // decode); rather than buffering it forever, evict it and report it so a caller
+            // can tell two games got mixed on one subscription.
+            for rejected_id in self.pending_genesis.drain(..) {
+                self.known.remove(&rejected_id);
+                self.children_of.remove(&rejected_id);
+                emitted.push(SubscriberEvent::Rejected {
+                    event_id: rejected_id,
+                });
+            }
+        }
+
+        while let Some(tip_id) = self.tip.clone() {
+            let Some(next_id) = self
+                .children_of
+                .remove(&tip_id)
+                .and_then(|children| children.into_iter().next())
+            else {
+                break;
+            };
+            match self.emit(&next_id) {
+                Some(action) => emitted.push(SubscriberEvent::Action(action)),
+                None => break,
+            }
+        }
+
+        emitted
+    }
+
+    /// Move `id` from `known` to emitted, advancing the tip, and convert it to a [`GameAction`].
+    fn emit(&mut self, id: &str) -> Option<GameAction> {
+        let (event, _) = self.known.remove(id)?;
+        let turn = find_tag(&event.tags, "turn")?.parse().ok()?;
+        let payload = hex::decode(&event.content).ok()?;
+
+        self.emitted_ids.insert(id.to_string());
+        self.tip = Some(id.to_string());
+        self.tip_turn = Some(turn);
+        self.last_reported_gap_turn = None;
+
+        Some(GameAction { turn, payload })
+    }
+
+    /// Evict any event still waiting on a missing predecessor past the window, and report a
+    /// gap if at least one was evicted and no gap was already reported at the current tip
+    /// turn. Without eviction, an event whose predecessor never
+    /// arrives (a dropped relay message, or an attacker-minted orphan citing a bogus `e` tag)
+    /// would sit in `known` forever, growing unboundedly.
+    fn sweep_timeouts(&mut self) -> Vec<SubscriberEvent> {
+        let now = Instant::now();
+        let reorder_window = self.reorder_window;
+        let stale_ids: Vec<String> = self
+            .known
+            .iter()
+            .filter(|(_, (_, arrived_at))| now.duration_since(*arrived_at) >= reorder_window)
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        if stale_ids.is_empty() {
+            return Vec::new();
+        }
+
+        for id in &stale_ids {
+            self.known.remove(id);
+            self.children_of.remove(id);
+            self.pending_genesis.retain(|pending| pending != id);
+        }
+        self.children_of.retain(|_, children| {
+            children.retain(|child| !stale_ids.contains(child));
+            !children.is_empty()
+        });
+
+        let after_turn = self.tip_turn.unwrap_or(0);
+        if self.last_reported_gap_turn == Some(after_turn) {
+            return Vec::new();
+        }
+        self.last_reported_gap_turn = Some(after_turn);
+        vec![SubscriberEvent::Gap { after_turn }]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use secp256k1::{Keypair, Secp256k1, SecretKey};
+
+    use crate::event::sign_action;
+
+    fn keypair() -> Keypair {
+        let secp = Secp256k1::new();
+        let secret = SecretKey::from_slice(&[0x11; 32]).expect("valid secret key");
+        Keypair::from_secret_key(&secp, &secret)
+    }
+
+    fn action_event(turn: u64, previous: Option<&str>) -> NostrEvent {
+        sign_action(
+            &GameAction {
+                turn,
+                payload: vec![turn as u8],
+            },
+            &keypair(),
+            1_700_000_000 + turn,
+            previous,
+        )
+        .expect("sign")
+    }
+
+    #[test]
+    fn emits_in_order_when_events_arrive_out_of_order() {
+        let genesis = action_event(0, None);
+        let second = action_event(1, Some(&genesis.id));
+        let third = action_event(2, Some(&second.id));
+
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(5));
+        assert!(buffer.ingest(third.clone()).is_empty());
+        assert!(buffer.ingest(second.clone()).is_empty());
+        let emitted = buffer.ingest(genesis.clone());
+
+        assert_eq!(
+            emitted,
+            vec![
+                SubscriberEvent::Action(GameAction { turn: 0, payload: vec![0] }),
+                SubscriberEvent::Action(GameAction { turn: 1, payload: vec![1] }),
+                SubscriberEvent::Action(GameAction { turn: 2, payload: vec![2] }),
+            ]
+        );
+    }
+
+    #[test]
+    fn duplicate_events_are_ignored() {
+        let genesis = action_event(0, None);
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(5));
+        assert_eq!(buffer.ingest(genesis.clone()).len(), 1);
+        assert!(buffer.ingest(genesis).is_empty());
+    }
+
+    #[test]
+    fn sweep_reports_gap_for_stalled_predecessor() {
+        let second = action_event(1, Some("never-arrives"));
+        let mut buffer = ReorderBuffer::new(Duration::from_millis(1));
+        assert!(buffer.ingest(second).is_empty());
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(
+            buffer.sweep_timeouts(),
+            vec![SubscriberEvent::Gap { after_turn: 0 }]
+        );
+    }
+
+    #[test]
+    fn sweep_evicts_stalled_orphans_instead_of_leaking_them() {
+        let mut buffer = ReorderBuffer::new(Duration::from_millis(1));
+        for turn in 0..50 {
+            let orphan = action_event(turn, Some("never-arrives"));
+            assert!(buffer.ingest(orphan).is_empty());
+        }
+        assert_eq!(buffer.known.len(), 50);
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert!(!buffer.sweep_timeouts().is_empty());
+        assert_eq!(buffer.known.len(), 0);
+        assert!(buffer.children_of.is_empty());
+    }
+
+    #[test]
+    fn second_genesis_is_rejected_and_not_leaked() {
+        let genesis = action_event(0, None);
+        let other_genesis = action_event(5, None);
+
+        let mut buffer = ReorderBuffer::new(Duration::from_secs(5));
+        assert_eq!(buffer.ingest(genesis.clone()).len(), 1);
+
+        let emitted = buffer.ingest(other_genesis.clone());
+        assert_eq!(
+            emitted,
+            vec![SubscriberEvent::Rejected {
+                event_id: other_genesis.id.clone()
+            }]
+        );
+        assert!(!buffer.known.contains_key(&other_genesis.id));
+    }
+}
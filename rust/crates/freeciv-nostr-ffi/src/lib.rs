@@ -1,12 +1,69 @@
 //! C FFI bindings for freeciv-nostr Rust crates. Exposes networking and crypto
 //! functions to the C game engine.
+//!
+//! No `fcn_*` entry point may panic across the FFI boundary: every one that returns a status
+//! catches unwinds via [`catch_unwind`] and translates both expected failures and panics into
+//! an integer status code (see the `FCN_*` constants), with the human-readable detail
+//! retrievable afterward via [`fcn_last_error`]. `fcn_client_destroy` and the `fcn_free_*`
+//! functions return nothing; they catch and discard a panic. This mirrors how libc-style APIs surface `errno` instead of unwinding
+//! across a language boundary.
 
-use std::ffi::CString;
-use std::os::raw::c_char;
-use std::sync::OnceLock;
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::ffi::{c_char, CStr, CString};
+use std::os::raw::c_int;
+use std::panic::catch_unwind;
+use std::ptr;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use freeciv_nostr_core::{
+    sign_action, DeadLetter, EventQueue, GameAction, GameStateSubscriber, QueueConfig,
+    SubscriberConfig, SubscriberEvent, SubscriptionFilter, WebsocketRelay, GAME_ACTION_KIND,
+};
+use secp256k1::{Keypair, Secp256k1, SecretKey};
+use tokio::runtime::Runtime;
 
 static VERSION: OnceLock<CString> = OnceLock::new();
 
+/// The call completed successfully.
+pub const FCN_OK: c_int = 0;
+/// A caller-supplied argument was null, malformed, or otherwise invalid.
+pub const FCN_INVALID_ARGUMENT: c_int = 1;
+/// An internal failure occurred (signing, queueing, or the async runtime).
+pub const FCN_INTERNAL_ERROR: c_int = 2;
+/// Nothing was available to return (e.g. no reconstructed action is queued yet).
+pub const FCN_NO_DATA: c_int = 3;
+/// The Rust implementation panicked; the panic was caught at the FFI boundary.
+pub const FCN_PANIC: c_int = 4;
+
+// Thread-local last-error slot, in the same spirit as the weak thread-local probes std uses
+// internally (e.g. for platform errno access): a simple, always-available slot rather than
+// unwinding or aborting when something goes wrong.
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: impl Into<String>) {
+    let sanitized = CString::new(message.into())
+        .unwrap_or_else(|_| CString::new("error message contained an interior nul byte").unwrap());
+    LAST_ERROR.with(|slot| *slot.borrow_mut() = Some(sanitized));
+}
+
+/// Return the most recent error message set by a failing `fcn_*` call on this thread, or null
+/// if there isn't one yet. The pointer is valid until the next `fcn_*` call on this thread.
+///
+/// # Safety
+///
+/// The caller must not free the returned pointer; it is owned by thread-local storage.
+#[unsafe(no_mangle)]
+pub extern "C" fn fcn_last_error() -> *const c_char {
+    LAST_ERROR.with(|slot| {
+        slot.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
 /// Return the freeciv-nostr library version as a C string.
 ///
 /// # Safety
@@ -21,6 +78,411 @@ pub extern "C" fn fcn_version() -> *const c_char {
         .as_ptr()
 }
 
+/// Failure reported by a fallible `fcn_*` call, distinct from a caught panic.
+struct FcnError {
+    status: c_int,
+    message: String,
+}
+
+impl FcnError {
+    fn invalid_argument(message: impl Into<String>) -> Self {
+        Self {
+            status: FCN_INVALID_ARGUMENT,
+            message: message.into(),
+        }
+    }
+
+    fn internal(message: impl Into<String>) -> Self {
+        Self {
+            status: FCN_INTERNAL_ERROR,
+            message: message.into(),
+        }
+    }
+
+    fn no_data(message: impl Into<String>) -> Self {
+        Self {
+            status: FCN_NO_DATA,
+            message: message.into(),
+        }
+    }
+}
+
+/// Run `body`, catching panics and translating both panics and returned errors into an
+/// `FCN_*` status code, recording the detail message in [`LAST_ERROR`].
+fn ffi_guard(body: impl FnOnce() -> Result<(), FcnError>) -> c_int {
+    match catch_unwind(std::panic::AssertUnwindSafe(body)) {
+        Ok(Ok(())) => FCN_OK,
+        Ok(Err(error)) => {
+            set_last_error(error.message);
+            error.status
+        }
+        Err(panic) => {
+            set_last_error(panic_message(&panic));
+            FCN_PANIC
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "panic with non-string payload".to_string()
+    }
+}
+
+/// Opaque handle to a running freeciv-nostr client: a signing key, a publish queue, and a
+/// relay subscriber, all driven by a private async runtime.
+pub struct FcnClient {
+    // `Option` so `Drop` can take it and shut it down in the background instead of blocking
+    // `fcn_client_destroy` on in-flight relay retries.
+    runtime: Option<Runtime>,
+    keypair: Keypair,
+    queue: EventQueue,
+    subscriber: Mutex<GameStateSubscriber>,
+    previous_event_id: Mutex<Option<String>>,
+    // Buffered ahead of `fcn_poll_dead_letter` so a `drain_dead_letters` call that turns up more
+    // than one dead letter at once doesn't silently discard the rest.
+    dead_letters: Mutex<VecDeque<DeadLetter>>,
+}
+
+impl Drop for FcnClient {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+impl FcnClient {
+    fn new(relays: Vec<String>, secret_key_hex: &str) -> Result<Self, FcnError> {
+        let secret_bytes = hex::decode(secret_key_hex)
+            .map_err(|_| FcnError::invalid_argument("secret key is not valid hex"))?;
+        let secret_key = SecretKey::from_slice(&secret_bytes)
+            .map_err(|err| FcnError::invalid_argument(format!("invalid secret key: {err}")))?;
+        let keypair = Keypair::from_secret_key(&Secp256k1::new(), &secret_key);
+
+        let runtime = Runtime::new()
+            .map_err(|err| FcnError::internal(format!("failed to start async runtime: {err}")))?;
+
+        let (queue, subscriber) = {
+            let _guard = runtime.enter();
+            let queue = EventQueue::spawn(
+                QueueConfig {
+                    relays: relays.clone(),
+                    ..QueueConfig::default()
+                },
+                Arc::new(WebsocketRelay),
+            )
+            .map_err(|err| FcnError::internal(format!("invalid queue config: {err}")))?;
+            let subscriber = GameStateSubscriber::spawn(
+                SubscriberConfig {
+                    relays,
+                    filter: SubscriptionFilter {
+                        kind: GAME_ACTION_KIND,
+                        authors: Vec::new(),
+                    },
+                    reorder_window: std::time::Duration::from_secs(10),
+                    initial_reconnect_backoff: std::time::Duration::from_secs(1),
+                    max_reconnect_backoff: std::time::Duration::from_secs(30),
+                },
+                Arc::new(WebsocketRelay),
+            );
+            (queue, subscriber)
+        };
+
+        Ok(Self {
+            runtime: Some(runtime),
+            keypair,
+            queue,
+            subscriber: Mutex::new(subscriber),
+            previous_event_id: Mutex::new(None),
+            dead_letters: Mutex::new(VecDeque::new()),
+        })
+    }
+
+    /// The client's private async runtime, for `block_on`-ing the core crate's async methods
+    /// from these synchronous FFI entry points.
+    fn runtime(&self) -> &Runtime {
+        self.runtime
+            .as_ref()
+            .expect("runtime is only taken by Drop, after which no fcn_* call can reach this")
+    }
+}
+
+/// Create a client handle from a list of relay URLs and a hex-encoded secp256k1 secret key.
+///
+/// On success, `*out_client` is set to a new handle that must later be released with
+/// [`fcn_client_destroy`].
+///
+/// # Safety
+///
+/// `relays` must point to `relay_count` valid, null-terminated UTF-8 C strings. `secret_key_hex`
+/// must be a valid null-terminated UTF-8 C string. `out_client` must be a valid, non-null,
+/// writable pointer.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_client_new(
+    relays: *const *const c_char,
+    relay_count: usize,
+    secret_key_hex: *const c_char,
+    out_client: *mut *mut FcnClient,
+) -> c_int {
+    ffi_guard(|| {
+        if (relay_count > 0 && relays.is_null())
+            || secret_key_hex.is_null()
+            || out_client.is_null()
+        {
+            return Err(FcnError::invalid_argument(
+                "fcn_client_new received a null pointer",
+            ));
+        }
+
+        let relay_urls = unsafe { c_string_array_to_vec(relays, relay_count) }?;
+        let secret_key_hex = unsafe { c_str_to_string(secret_key_hex) }?;
+        let client = FcnClient::new(relay_urls, &secret_key_hex)?;
+
+        unsafe {
+            *out_client = Box::into_raw(Box::new(client));
+        }
+        Ok(())
+    })
+}
+
+/// Release a client handle created by [`fcn_client_new`].
+///
+/// # Safety
+///
+/// `client` must be a pointer previously returned by [`fcn_client_new`] and not already
+/// destroyed. Passing null is a no-op.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_client_destroy(client: *mut FcnClient) {
+    if client.is_null() {
+        return;
+    }
+    let _ = catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+        drop(Box::from_raw(client));
+    }));
+}
+
+/// Sign and hand off a turn's action bytes to the background publish queue, chaining it to the
+/// client's previously published event.
+///
+/// [`FCN_OK`] means the event was accepted onto the queue, not that any relay has confirmed it
+/// yet — the queue retries each relay independently in the background. Poll
+/// [`fcn_poll_dead_letter`] to learn about publishes that exhausted their retries.
+///
+/// # Safety
+///
+/// `client` must be a valid pointer from [`fcn_client_new`]. `payload` must point to
+/// `payload_len` readable bytes (or be null only if `payload_len` is zero).
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_publish_action(
+    client: *mut FcnClient,
+    turn: u64,
+    payload: *const u8,
+    payload_len: usize,
+) -> c_int {
+    ffi_guard(|| {
+        let client = unsafe { client.as_ref() }
+            .ok_or_else(|| FcnError::invalid_argument("null client pointer"))?;
+        let payload = unsafe { byte_slice(payload, payload_len) }?.to_vec();
+
+        let action = GameAction { turn, payload };
+        let mut previous_event_id = client.previous_event_id.lock().unwrap();
+        let created_at = unix_now();
+        let event = sign_action(
+            &action,
+            &client.keypair,
+            created_at,
+            previous_event_id.as_deref(),
+        )
+        .map_err(|err| FcnError::internal(format!("failed to sign action: {err}")))?;
+
+        client
+            .queue
+            .enqueue(event.clone())
+            .map_err(|err| FcnError::internal(format!("failed to enqueue action: {err}")))?;
+        *previous_event_id = Some(event.id);
+        Ok(())
+    })
+}
+
+/// Poll for the next reconstructed game action from the subscription.
+///
+/// On [`FCN_OK`], `*out_turn`, `*out_payload`, and `*out_payload_len` are populated; the
+/// payload buffer must be released with [`fcn_free_buffer`]. On [`FCN_NO_DATA`], no action was
+/// available yet and the output parameters are left untouched.
+///
+/// # Safety
+///
+/// `client` must be a valid pointer from [`fcn_client_new`]. `out_turn`, `out_payload`, and
+/// `out_payload_len` must be valid, non-null, writable pointers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_poll_action(
+    client: *mut FcnClient,
+    out_turn: *mut u64,
+    out_payload: *mut *mut u8,
+    out_payload_len: *mut usize,
+) -> c_int {
+    ffi_guard(|| {
+        let client = unsafe { client.as_ref() }
+            .ok_or_else(|| FcnError::invalid_argument("null client pointer"))?;
+        if out_turn.is_null() || out_payload.is_null() || out_payload_len.is_null() {
+            return Err(FcnError::invalid_argument(
+                "fcn_poll_action received a null output pointer",
+            ));
+        }
+
+        let mut subscriber = client.subscriber.lock().unwrap();
+        match subscriber.try_recv() {
+            Some(SubscriberEvent::Action(action)) => {
+                let mut buffer = action.payload.into_boxed_slice();
+                unsafe {
+                    *out_turn = action.turn;
+                    *out_payload_len = buffer.len();
+                    *out_payload = buffer.as_mut_ptr();
+                }
+                std::mem::forget(buffer);
+                Ok(())
+            }
+            Some(SubscriberEvent::Gap { after_turn }) => Err(FcnError::no_data(format!(
+                "gap detected after turn {after_turn}; backfill required"
+            ))),
+            Some(SubscriberEvent::Rejected { event_id }) => Err(FcnError::no_data(format!(
+                "event {event_id} was a second genesis (no e tag) and was discarded"
+            ))),
+            None => Err(FcnError::no_data("no action available yet")),
+        }
+    })
+}
+
+/// Poll for the next publish that exhausted its retries against a relay.
+///
+/// On [`FCN_OK`], `*out_relay` and `*out_reason` are populated with newly-allocated C strings
+/// that must be released with [`fcn_free_string`]. On [`FCN_NO_DATA`], nothing has dead-lettered
+/// since the last call and the output parameters are left untouched.
+///
+/// # Safety
+///
+/// `client` must be a valid pointer from [`fcn_client_new`]. `out_relay` and `out_reason` must
+/// be valid, non-null, writable pointers.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_poll_dead_letter(
+    client: *mut FcnClient,
+    out_relay: *mut *mut c_char,
+    out_reason: *mut *mut c_char,
+) -> c_int {
+    ffi_guard(|| {
+        let client = unsafe { client.as_ref() }
+            .ok_or_else(|| FcnError::invalid_argument("null client pointer"))?;
+        if out_relay.is_null() || out_reason.is_null() {
+            return Err(FcnError::invalid_argument(
+                "fcn_poll_dead_letter received a null output pointer",
+            ));
+        }
+
+        let mut dead_letters = client.dead_letters.lock().unwrap();
+        if dead_letters.is_empty() {
+            let drained = client.runtime().block_on(client.queue.drain_dead_letters());
+            dead_letters.extend(drained);
+        }
+
+        let Some(dead_letter) = dead_letters.pop_front() else {
+            return Err(FcnError::no_data("no dead letters available yet"));
+        };
+
+        let relay = CString::new(dead_letter.relay)
+            .map_err(|_| FcnError::internal("dead letter relay contained an interior nul byte"))?;
+        let reason = CString::new(dead_letter.last_error.reason).map_err(|_| {
+            FcnError::internal("dead letter reason contained an interior nul byte")
+        })?;
+        unsafe {
+            *out_relay = relay.into_raw();
+            *out_reason = reason.into_raw();
+        }
+        Ok(())
+    })
+}
+
+/// Free a string returned by [`fcn_poll_dead_letter`].
+///
+/// # Safety
+///
+/// `ptr` must be exactly a pointer most recently returned by [`fcn_poll_dead_letter`], not yet
+/// freed. Passing null is a no-op.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_free_string(ptr: *mut c_char) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+        drop(CString::from_raw(ptr));
+    }));
+}
+
+/// Free a payload buffer returned by [`fcn_poll_action`].
+///
+/// # Safety
+///
+/// `ptr`/`len` must be exactly the pair most recently returned by [`fcn_poll_action`] for this
+/// buffer, not yet freed. Passing null is a no-op.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fcn_free_buffer(ptr: *mut u8, len: usize) {
+    if ptr.is_null() {
+        return;
+    }
+    let _ = catch_unwind(std::panic::AssertUnwindSafe(|| unsafe {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+    }));
+}
+
+unsafe fn c_str_to_string(ptr: *const c_char) -> Result<String, FcnError> {
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map(str::to_string)
+        .map_err(|_| FcnError::invalid_argument("string argument was not valid UTF-8"))
+}
+
+unsafe fn c_string_array_to_vec(
+    ptr: *const *const c_char,
+    len: usize,
+) -> Result<Vec<String>, FcnError> {
+    if len == 0 {
+        return Ok(Vec::new());
+    }
+    let entries = unsafe { std::slice::from_raw_parts(ptr, len) };
+    entries
+        .iter()
+        .map(|&entry| {
+            if entry.is_null() {
+                return Err(FcnError::invalid_argument("relay list contained a null entry"));
+            }
+            unsafe { c_str_to_string(entry) }
+        })
+        .collect()
+}
+
+unsafe fn byte_slice<'a>(ptr: *const u8, len: usize) -> Result<&'a [u8], FcnError> {
+    if len == 0 {
+        return Ok(&[]);
+    }
+    if ptr.is_null() {
+        return Err(FcnError::invalid_argument(
+            "non-zero length payload had a null pointer",
+        ));
+    }
+    Ok(unsafe { std::slice::from_raw_parts(ptr, len) })
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .expect("system clock is after the Unix epoch")
+        .as_secs()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -33,4 +495,77 @@ mod tests {
         let cstr = unsafe { CStr::from_ptr(ptr) };
         assert_eq!(cstr.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
     }
+
+    #[test]
+    fn last_error_is_null_until_a_call_fails() {
+        assert!(fcn_last_error().is_null());
+    }
+
+    #[test]
+    fn client_new_rejects_null_pointers() {
+        let mut out_client: *mut FcnClient = ptr::null_mut();
+        let status = unsafe { fcn_client_new(ptr::null(), 0, ptr::null(), &mut out_client) };
+        assert_eq!(status, FCN_INVALID_ARGUMENT);
+        assert!(!fcn_last_error().is_null());
+    }
+
+    #[test]
+    fn client_new_rejects_invalid_hex_secret_key() {
+        let secret = CString::new("not-hex").unwrap();
+        let mut out_client: *mut FcnClient = ptr::null_mut();
+        let status =
+            unsafe { fcn_client_new(ptr::null(), 0, secret.as_ptr(), &mut out_client) };
+        assert_eq!(status, FCN_INVALID_ARGUMENT);
+    }
+
+    #[test]
+    fn full_client_lifecycle_publishes_and_polls_without_panicking() {
+        let relay = CString::new("wss://example.invalid").unwrap();
+        let relays = [relay.as_ptr()];
+        let secret = CString::new("21".repeat(32)).unwrap();
+
+        let mut client: *mut FcnClient = ptr::null_mut();
+        let status =
+            unsafe { fcn_client_new(relays.as_ptr(), relays.len(), secret.as_ptr(), &mut client) };
+        assert_eq!(status, FCN_OK);
+        assert!(!client.is_null());
+
+        let payload = [1u8, 2, 3];
+        let status =
+            unsafe { fcn_publish_action(client, 1, payload.as_ptr(), payload.len()) };
+        assert_eq!(status, FCN_OK);
+
+        let mut turn = 0u64;
+        let mut out_payload: *mut u8 = ptr::null_mut();
+        let mut out_len: usize = 0;
+        let status =
+            unsafe { fcn_poll_action(client, &mut turn, &mut out_payload, &mut out_len) };
+        assert_eq!(status, FCN_NO_DATA);
+
+        let mut out_relay: *mut c_char = ptr::null_mut();
+        let mut out_reason: *mut c_char = ptr::null_mut();
+        let status = unsafe { fcn_poll_dead_letter(client, &mut out_relay, &mut out_reason) };
+        assert_eq!(status, FCN_NO_DATA);
+        assert!(out_relay.is_null());
+        assert!(out_reason.is_null());
+
+        unsafe { fcn_client_destroy(client) };
+    }
+
+    #[test]
+    fn poll_dead_letter_rejects_null_output_pointers() {
+        let relay = CString::new("wss://example.invalid").unwrap();
+        let relays = [relay.as_ptr()];
+        let secret = CString::new("22".repeat(32)).unwrap();
+
+        let mut client: *mut FcnClient = ptr::null_mut();
+        let status =
+            unsafe { fcn_client_new(relays.as_ptr(), relays.len(), secret.as_ptr(), &mut client) };
+        assert_eq!(status, FCN_OK);
+
+        let status = unsafe { fcn_poll_dead_letter(client, ptr::null_mut(), ptr::null_mut()) };
+        assert_eq!(status, FCN_INVALID_ARGUMENT);
+
+        unsafe { fcn_client_destroy(client) };
+    }
 }